@@ -0,0 +1,200 @@
+//! Authentication strategies that `Configuration` attaches to every
+//! outgoing request as an `Authorization` header: none, a bare bearer
+//! token, or an IoT Hub shared-access-signature (SAS) token that is
+//! generated on demand and refreshed shortly before it expires.
+
+use std::cell::RefCell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub enum AuthType {
+    None,
+    Bearer(String),
+    Sas(SasTokenSource),
+}
+
+impl AuthType {
+    /// Returns the value to set on the `Authorization` header, refreshing
+    /// a cached SAS token first if it is due to expire.
+    pub fn header_value(&self) -> Result<Option<String>, Error> {
+        match *self {
+            AuthType::None => Ok(None),
+            AuthType::Bearer(ref token) => Ok(Some(format!("Bearer {}", token))),
+            AuthType::Sas(ref source) => source.token().map(Some),
+        }
+    }
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: u64,
+}
+
+/// Generates and caches IoT Hub SAS tokens for a single resource URI.
+pub struct SasTokenSource {
+    resource_uri: String,
+    shared_key: Vec<u8>,
+    policy_name: String,
+    ttl_secs: u64,
+    refresh_window_secs: u64,
+    cached: RefCell<Option<CachedToken>>,
+}
+
+impl SasTokenSource {
+    /// `key_base64` is the base64-encoded shared access key from IoT Hub.
+    /// The token is refreshed once it is within `refresh_window_secs` of
+    /// expiring.
+    pub fn new(
+        resource_uri: String,
+        key_base64: &str,
+        policy_name: String,
+        ttl_secs: u64,
+        refresh_window_secs: u64,
+    ) -> Result<Self, Error> {
+        let shared_key = base64::decode(key_base64)
+            .map_err(|err| Error::Api(format!("invalid shared access key: {}", err)))?;
+
+        Ok(SasTokenSource {
+            resource_uri,
+            shared_key,
+            policy_name,
+            ttl_secs,
+            refresh_window_secs,
+            cached: RefCell::new(None),
+        })
+    }
+
+    pub fn token(&self) -> Result<String, Error> {
+        let now = now_secs();
+
+        if let Some(ref cached) = *self.cached.borrow() {
+            if cached.expires_at > now + self.refresh_window_secs {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let expires_at = now + self.ttl_secs;
+        let token = sign(&self.resource_uri, &self.shared_key, &self.policy_name, expires_at)?;
+        *self.cached.borrow_mut() = Some(CachedToken {
+            token: token.clone(),
+            expires_at,
+        });
+        Ok(token)
+    }
+}
+
+fn sign(resource_uri: &str, key: &[u8], policy_name: &str, expires_at: u64) -> Result<String, Error> {
+    let encoded_uri = percent_encode(resource_uri);
+    let to_sign = format!("{}\n{}", encoded_uri, expires_at);
+
+    let mut mac = HmacSha256::new_varkey(key)
+        .map_err(|_| Error::Api("shared access key has an invalid length for HMAC-SHA256".to_owned()))?;
+    mac.input(to_sign.as_bytes());
+    let signature = base64::encode(mac.result().code().as_slice());
+    let encoded_sig = percent_encode(&signature);
+
+    let mut token = format!(
+        "SharedAccessSignature sr={}&sig={}&se={}",
+        encoded_uri, encoded_sig, expires_at
+    );
+    if !policy_name.is_empty() {
+        token.push_str(&format!("&skn={}", policy_name));
+    }
+    Ok(token)
+}
+
+/// Percent-encodes every byte that is not an RFC 3986 "unreserved"
+/// character (`A-Z a-z 0-9 - . _ ~`). The SAS token is a `key=value&...`
+/// string, and both the resource URI and the base64-encoded signature can
+/// contain `+`, `/`, `=`, `&` and other bytes that a form/query decoder on
+/// the receiving end would otherwise reinterpret, silently corrupting the
+/// signature IoT Hub reconstructs. This is equivalent to Python's
+/// `urllib.parse.quote(..., safe='')`, and stricter than `encodeURIComponent`,
+/// which leaves `!'()*` unescaped.
+fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encode_escapes_everything_but_unreserved_characters() {
+        assert_eq!(percent_encode("abcXYZ019-._~"), "abcXYZ019-._~");
+        // Base64 output bytes that a form/query decoder would otherwise
+        // misinterpret (`+` as space, `/` and `=` as delimiters) must all
+        // be escaped.
+        assert_eq!(percent_encode("a+b/c=d"), "a%2Bb%2Fc%3Dd");
+        assert_eq!(percent_encode("myhub.azure-devices.net/devices/dev1"), "myhub.azure-devices.net%2Fdevices%2Fdev1");
+    }
+
+    #[test]
+    fn sign_matches_a_known_hmac_sha256_vector() {
+        // Computed independently (Python `hmac`/`hashlib`) for the same
+        // inputs, so this pins both the HMAC-SHA256 computation and the
+        // percent-encoding of the resulting signature.
+        let key = base64::decode("a2V5MTIzNA==").unwrap();
+        let token = sign(
+            "myhub.azure-devices.net/devices/dev1",
+            &key,
+            "iothubowner",
+            1_000_000_000,
+        )
+        .unwrap();
+
+        assert_eq!(
+            token,
+            "SharedAccessSignature \
+             sr=myhub.azure-devices.net%2Fdevices%2Fdev1&\
+             sig=CC0Ze6lJMZ0H1aSRWnRM1K%2BHTw52GoCfv%2Ft0QBBO0M4%3D&\
+             se=1000000000&skn=iothubowner"
+        );
+    }
+
+    #[test]
+    fn sign_omits_skn_when_policy_name_is_empty() {
+        let key = base64::decode("a2V5MTIzNA==").unwrap();
+        let token = sign("myhub.azure-devices.net/devices/dev1", &key, "", 1_000_000_000).unwrap();
+        assert!(!token.contains("skn="));
+    }
+
+    #[test]
+    fn token_source_caches_until_the_refresh_window() {
+        let source = SasTokenSource::new(
+            "myhub.azure-devices.net/devices/dev1".to_owned(),
+            "a2V5MTIzNA==",
+            "iothubowner".to_owned(),
+            3_600,
+            300,
+        )
+        .unwrap();
+
+        let first = source.token().unwrap();
+        let second = source.token().unwrap();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,22 @@
+use std::rc::Rc;
+
+use super::configuration::Configuration;
+use super::service_api::{ServiceApi, ServiceApiClient};
+
+pub struct APIClient {
+    service_api: Box<ServiceApi>,
+}
+
+impl APIClient {
+    pub fn new(configuration: Configuration) -> Self {
+        let rc = Rc::new(configuration);
+
+        APIClient {
+            service_api: Box::new(ServiceApiClient::new(rc.clone())),
+        }
+    }
+
+    pub fn service_api(&self) -> &ServiceApi {
+        self.service_api.as_ref()
+    }
+}
@@ -0,0 +1,193 @@
+use std::io::BufReader;
+use std::path::Path;
+use std::rc::Rc;
+
+use hyper;
+use hyper::client::{Client, HttpConnector};
+use hyper_rustls::HttpsConnector;
+use hyperlocal::UnixConnector;
+use rustls;
+use tokio_core::reactor::Handle;
+
+use super::auth::AuthType;
+use super::monitor::{Monitor, NoopMonitor};
+use super::retry::RetryPolicy;
+use super::trust::{Targets, TrustStore};
+use super::Error;
+
+/// The transports the generated `apis` methods can be routed over, one per
+/// `base_path` scheme (`http://`, `https://`, `unix://`), so the same client
+/// code works unchanged against any of them. The caller picks the variant
+/// that matches its `base_path`; it is not derived from the scheme.
+pub enum Connector {
+    Http(Client<HttpConnector>),
+    Https(Client<HttpsConnector<HttpConnector>>),
+    Unix(Client<UnixConnector>),
+}
+
+impl Connector {
+    pub fn http(handle: &Handle) -> Connector {
+        Connector::Http(Client::configure().connector(HttpConnector::new(4, handle)).build(handle))
+    }
+
+    /// Builds a TLS connector, optionally trusting a custom CA bundle and/or
+    /// presenting a client certificate for mutual TLS.
+    pub fn https(
+        handle: &Handle,
+        ca_bundle: Option<&Path>,
+        client_cert: Option<(&Path, &Path)>,
+    ) -> Result<Connector, Error> {
+        let mut http = HttpConnector::new(4, handle);
+        http.enforce_http(false);
+
+        let mut tls_config = rustls::ClientConfig::new();
+        if let Some(ca_bundle) = ca_bundle {
+            let file = ::std::fs::File::open(ca_bundle)?;
+            let mut reader = BufReader::new(file);
+            tls_config
+                .root_store
+                .add_pem_file(&mut reader)
+                .map_err(|()| Error::Api(format!("invalid CA bundle {}", ca_bundle.display())))?;
+        } else {
+            tls_config
+                .root_store
+                .add_server_trust_anchors(&::webpki_roots::TLS_SERVER_ROOTS);
+        }
+
+        if let Some((cert_path, key_path)) = client_cert {
+            let cert_file = ::std::fs::File::open(cert_path)?;
+            let certs = rustls::internal::pemfile::certs(&mut BufReader::new(cert_file))
+                .map_err(|()| Error::Api(format!("invalid client certificate {}", cert_path.display())))?;
+            let key_file = ::std::fs::File::open(key_path)?;
+            let mut keys = rustls::internal::pemfile::pkcs8_private_keys(&mut BufReader::new(key_file))
+                .map_err(|()| Error::Api(format!("invalid client key {}", key_path.display())))?;
+            let key = keys
+                .pop()
+                .ok_or_else(|| Error::Api(format!("no private key found in {}", key_path.display())))?;
+            tls_config
+                .set_single_client_cert(certs, key)
+                .map_err(|err| Error::Api(format!("invalid client certificate/key pair: {}", err)))?;
+        }
+
+        let https = HttpsConnector::from((http, tls_config));
+        Ok(Connector::Https(Client::configure().connector(https).build(handle)))
+    }
+
+    pub fn unix(handle: &Handle) -> Connector {
+        Connector::Unix(
+            Client::configure()
+                .connector(UnixConnector::new(handle.clone()))
+                .build(handle),
+        )
+    }
+
+    pub fn request(&self, req: hyper::Request) -> hyper::client::FutureResponse {
+        match *self {
+            Connector::Http(ref client) => client.request(req),
+            Connector::Https(ref client) => client.request(req),
+            Connector::Unix(ref client) => client.request(req),
+        }
+    }
+}
+
+pub struct Configuration {
+    pub base_path: String,
+    pub user_agent: Option<String>,
+    pub connector: Connector,
+    pub auth: AuthType,
+    pub retry_policy: RetryPolicy,
+    /// Whether non-idempotent calls (e.g. `apply_configuration_content`)
+    /// are allowed to retry transient failures. Off by default since
+    /// retrying a non-idempotent call can duplicate its side effects.
+    pub retry_non_idempotent: bool,
+    pub monitor: Rc<Monitor>,
+    /// When set, responses are checked against `trusted_targets` with
+    /// `trust_store` before the generated `apis` methods hand them to
+    /// callers. Unset by default: trust pinning is opt-in, since it
+    /// requires the caller to have fetched and verified a `root` and a
+    /// current `targets` metadata out of band first.
+    pub trust_store: Option<TrustStore>,
+    pub trusted_targets: Option<Targets>,
+}
+
+impl Configuration {
+    pub fn new(base_path: String, connector: Connector) -> Self {
+        Configuration {
+            base_path,
+            user_agent: Some("Swagger-Codegen/1.0.0/rust".to_owned()),
+            connector,
+            auth: AuthType::None,
+            retry_policy: RetryPolicy::default(),
+            retry_non_idempotent: false,
+            monitor: Rc::new(NoopMonitor),
+            trust_store: None,
+            trusted_targets: None,
+        }
+    }
+
+    /// Pins a verified TUF trust root and the `targets` metadata it
+    /// vouches for, so that subsequent calls can check response bodies
+    /// against it (see `ServiceApiClient::get_module`).
+    pub fn with_trust(mut self, trust_store: TrustStore, trusted_targets: Targets) -> Self {
+        self.trust_store = Some(trust_store);
+        self.trusted_targets = Some(trusted_targets);
+        self
+    }
+
+    /// Resolves `path` (e.g. `/devices/foo`) against `base_path`, rewriting
+    /// `unix://<socket-path>` base paths into the `hyperlocal` URI form that
+    /// encodes both the socket path and the request path in one `Uri`.
+    pub fn uri(&self, path: &str) -> Result<hyper::Uri, Error> {
+        if self.base_path.starts_with("unix://") {
+            let socket_path = &self.base_path["unix://".len()..];
+            return Ok(::hyperlocal::Uri::new(socket_path, path).into());
+        }
+
+        format!("{}{}", self.base_path, path)
+            .parse()
+            .map_err(|err| Error::Api(format!("invalid URI: {}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio_core::reactor::Core;
+
+    fn configuration(base_path: &str) -> Configuration {
+        let core = Core::new().expect("reactor core");
+        Configuration::new(base_path.to_owned(), Connector::http(&core.handle()))
+    }
+
+    #[test]
+    fn http_base_path_passes_through_unchanged() {
+        let config = configuration("http://localhost:8080");
+        let uri = config.uri("/devices/foo").expect("valid uri");
+        assert_eq!(uri.to_string(), "http://localhost:8080/devices/foo");
+    }
+
+    #[test]
+    fn https_base_path_passes_through_unchanged() {
+        let config = configuration("https://example.com");
+        let uri = config.uri("/devices/foo").expect("valid uri");
+        assert_eq!(uri.to_string(), "https://example.com/devices/foo");
+    }
+
+    #[test]
+    fn unix_base_path_is_rewritten_into_a_hyperlocal_uri_that_keeps_the_request_path() {
+        let config = configuration("unix:///var/run/iotedge/mgmt.sock");
+        let uri = config.uri("/devices/foo").expect("valid uri");
+        assert!(
+            uri.to_string().ends_with("/devices/foo"),
+            "expected the request path to survive the unix:// rewrite, got {}",
+            uri
+        );
+    }
+
+    #[test]
+    fn malformed_base_path_is_an_error() {
+        let config = configuration("not a valid base path");
+        assert!(config.uri("/devices/foo").is_err());
+    }
+}
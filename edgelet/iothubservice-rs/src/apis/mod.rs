@@ -0,0 +1,57 @@
+use hyper;
+use serde_json;
+use std::io;
+
+pub mod auth;
+pub mod client;
+pub mod configuration;
+pub mod monitor;
+pub mod retry;
+pub mod service_api;
+pub mod trust;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "hyper error: {}", _0)]
+    Hyper(#[cause] hyper::Error),
+    #[fail(display = "serde error: {}", _0)]
+    Serde(#[cause] serde_json::Error),
+    #[fail(display = "io error: {}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "API error: {}", _0)]
+    Api(String),
+    #[fail(display = "{}", _0)]
+    Retry(#[cause] Box<retry::RetryError>),
+    #[fail(display = "trust verification failed: {}", _0)]
+    Trust(#[cause] trust::TrustError),
+}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Error::Hyper(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Serde(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<retry::RetryError> for Error {
+    fn from(e: retry::RetryError) -> Self {
+        Error::Retry(Box::new(e))
+    }
+}
+
+impl From<trust::TrustError> for Error {
+    fn from(e: trust::TrustError) -> Self {
+        Error::Trust(e)
+    }
+}
@@ -0,0 +1,40 @@
+//! Pluggable error-reporting hook invoked whenever an `apis` call fails
+//! outright or a response comes back with a non-2xx status (including a
+//! 1xx/3xx that `retry::execute` otherwise passes through to the caller as
+//! a success), so callers can wire failures into their own telemetry sink
+//! (logging, metrics, a crash reporter) without the generated methods
+//! changing their public return types.
+
+use std::time::Duration;
+
+/// What happened to a single request attempt.
+pub enum Outcome<'a> {
+    Status(u16),
+    Error(&'a str),
+}
+
+pub trait Monitor {
+    fn on_failure(&self, operation: &str, uri: &str, outcome: &Outcome, latency: Duration);
+}
+
+/// Default monitor: observes nothing.
+pub struct NoopMonitor;
+
+impl Monitor for NoopMonitor {
+    fn on_failure(&self, _operation: &str, _uri: &str, _outcome: &Outcome, _latency: Duration) {}
+}
+
+/// Logs failures via the `log` facade at `warn` level.
+pub struct LoggingMonitor;
+
+impl Monitor for LoggingMonitor {
+    fn on_failure(&self, operation: &str, uri: &str, outcome: &Outcome, latency: Duration) {
+        match *outcome {
+            Outcome::Status(status) => warn!(
+                "{} {} failed with status {} after {:?}",
+                operation, uri, status, latency
+            ),
+            Outcome::Error(err) => warn!("{} {} failed: {} (after {:?})", operation, uri, err, latency),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Retries transient failures for idempotent (and explicitly opted-in)
+//! `apis` calls using exponential backoff with full jitter: wait
+//! `min(cap, base * 2^attempt)`, then scale by a random factor in
+//! `[0, 1)`. A `Retry-After` response header, when present, overrides the
+//! computed delay.
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::future::{loop_fn, Loop};
+use futures::{future, Future};
+use hyper;
+use rand::Rng;
+use tokio_timer::Timer;
+
+use super::configuration::Configuration;
+use super::monitor::Outcome;
+use super::Error;
+
+#[derive(Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base: Duration,
+    pub cap: Duration,
+    pub retry_status_codes: Vec<u16>,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, base: Duration, cap: Duration) -> Self {
+        RetryPolicy {
+            max_retries,
+            base,
+            cap,
+            retry_status_codes: vec![429, 502, 503, 504],
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = 2u32.checked_pow(attempt).unwrap_or(u32::max_value());
+        let uncapped = self.base.checked_mul(exp).unwrap_or(self.cap);
+        let capped = if uncapped > self.cap { self.cap } else { uncapped };
+
+        let jitter: f64 = rand::thread_rng().gen();
+        let capped_millis = capped.as_secs() * 1_000 + u64::from(capped.subsec_nanos()) / 1_000_000;
+        Duration::from_millis((capped_millis as f64 * jitter) as u64)
+    }
+
+    fn is_retryable_status(&self, status: u16) -> bool {
+        self.retry_status_codes.contains(&status)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(3, Duration::from_millis(100), Duration::from_secs(5))
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "request failed after {} attempt(s): {}", attempts, cause)]
+pub struct RetryError {
+    pub attempts: u32,
+    #[cause]
+    pub cause: Error,
+}
+
+fn retry_after(resp: &hyper::Response) -> Option<Duration> {
+    resp.headers()
+        .get_raw("Retry-After")
+        .and_then(|raw| raw.one())
+        .and_then(|bytes| ::std::str::from_utf8(bytes).ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Runs `build_request` (which must build a fresh `hyper::Request` every
+/// call, since a `Request` is consumed by the connector) up to
+/// `policy.max_retries` additional times when `retryable` is set and the
+/// failure looks transient.
+pub fn execute<F>(
+    configuration: Rc<Configuration>,
+    policy: RetryPolicy,
+    retryable: bool,
+    operation: &'static str,
+    build_request: F,
+) -> Box<Future<Item = hyper::Response, Error = RetryError>>
+where
+    F: Fn() -> Result<hyper::Request, Error> + 'static,
+{
+    let max_retries = if retryable { policy.max_retries } else { 0 };
+
+    Box::new(loop_fn(0u32, move |attempt| {
+        let configuration = Rc::clone(&configuration);
+        let policy = policy.clone();
+
+        let req = match build_request() {
+            Ok(req) => req,
+            Err(err) => {
+                return Box::new(future::err(RetryError {
+                    attempts: attempt + 1,
+                    cause: err,
+                })) as Box<Future<Item = Loop<hyper::Response, u32>, Error = RetryError>>
+            }
+        };
+        let uri = req.uri().to_string();
+        let started_at = Instant::now();
+
+        Box::new(configuration.connector.request(req).then(move |result| {
+            let latency = started_at.elapsed();
+            match result {
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    if status >= 200 && status < 300 {
+                        return future::Either::A(future::ok(Loop::Break(resp)));
+                    }
+                    // Every non-2xx status is reported to the monitor hook,
+                    // even a 1xx/3xx that isn't an error: callers rely on
+                    // the hook to see those, not just 4xx/5xx.
+                    configuration
+                        .monitor
+                        .on_failure(operation, &uri, &Outcome::Status(status), latency);
+                    if status < 400 {
+                        return future::Either::A(future::ok(Loop::Break(resp)));
+                    }
+                    if attempt >= max_retries || !policy.is_retryable_status(status) {
+                        let err = Error::Api(format!("request failed with status {}", status));
+                        return future::Either::A(future::err(RetryError {
+                            attempts: attempt + 1,
+                            cause: err,
+                        }));
+                    }
+                    let delay = retry_after(&resp).unwrap_or_else(|| policy.backoff(attempt));
+                    future::Either::B(
+                        Timer::default()
+                            .sleep(delay)
+                            .then(move |_| Ok(Loop::Continue(attempt + 1))),
+                    )
+                }
+                Err(err) => {
+                    let err = Error::from(err);
+                    configuration.monitor.on_failure(
+                        operation,
+                        &uri,
+                        &Outcome::Error(&err.to_string()),
+                        latency,
+                    );
+                    if attempt >= max_retries {
+                        future::Either::A(future::err(RetryError {
+                            attempts: attempt + 1,
+                            cause: err,
+                        }))
+                    } else {
+                        let delay = policy.backoff(attempt);
+                        future::Either::B(
+                            Timer::default()
+                                .sleep(delay)
+                                .then(move |_| Ok(Loop::Continue(attempt + 1))),
+                        )
+                    }
+                }
+            }
+        }))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_never_exceeds_the_cap_even_for_large_attempt_numbers() {
+        let policy = RetryPolicy::new(10, Duration::from_millis(100), Duration::from_secs(5));
+        // `2u32.checked_pow(attempt)` overflows well before `u32::max_value()`,
+        // and `Duration::checked_mul` can overflow too; both must fall back
+        // to the cap rather than panicking.
+        for attempt in &[0u32, 5, 10, 31, 32, 1_000, u32::max_value()] {
+            let delay = policy.backoff(*attempt);
+            assert!(
+                delay <= policy.cap,
+                "attempt {} produced {:?}, expected <= cap {:?}",
+                attempt,
+                delay,
+                policy.cap
+            );
+        }
+    }
+
+    #[test]
+    fn backoff_stays_within_the_uncapped_exponential_ceiling_before_the_cap_is_reached() {
+        let policy = RetryPolicy::new(10, Duration::from_millis(100), Duration::from_secs(100));
+        // Jitter scales the delay by a random factor in [0, 1), so this
+        // pins the upper bound rather than an exact value.
+        assert!(policy.backoff(0) <= policy.base);
+        assert!(policy.backoff(3) <= policy.base * 8);
+    }
+
+    #[test]
+    fn is_retryable_status_matches_only_the_configured_codes() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable_status(503));
+        assert!(policy.is_retryable_status(429));
+        assert!(!policy.is_retryable_status(200));
+        assert!(!policy.is_retryable_status(404));
+    }
+
+    #[test]
+    fn retry_after_parses_a_well_formed_header() {
+        let mut resp = hyper::Response::new();
+        resp.headers_mut().set_raw("Retry-After", "120");
+        assert_eq!(retry_after(&resp), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn retry_after_ignores_a_non_numeric_header() {
+        let mut resp = hyper::Response::new();
+        resp.headers_mut().set_raw("Retry-After", "soon");
+        assert_eq!(retry_after(&resp), None);
+    }
+
+    #[test]
+    fn retry_after_is_none_when_the_header_is_absent() {
+        let resp = hyper::Response::new();
+        assert_eq!(retry_after(&resp), None);
+    }
+}
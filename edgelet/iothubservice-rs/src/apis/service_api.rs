@@ -0,0 +1,149 @@
+use std::rc::Rc;
+
+use futures::{future, Future, Stream};
+use hyper;
+use hyper::header::{ContentType, UserAgent};
+use ring::digest;
+use serde_json;
+
+use super::configuration::Configuration;
+use super::retry;
+use super::trust;
+use super::Error;
+use models::{DeploymentConfigInfo, ModuleSpec};
+
+pub trait ServiceApi {
+    fn get_module(
+        &self,
+        device_id: &str,
+        module_id: &str,
+    ) -> Box<Future<Item = ModuleSpec, Error = Error>>;
+
+    fn apply_configuration_content(
+        &self,
+        device_id: &str,
+        content: &DeploymentConfigInfo,
+    ) -> Box<Future<Item = (), Error = Error>>;
+}
+
+pub struct ServiceApiClient {
+    configuration: Rc<Configuration>,
+}
+
+impl ServiceApiClient {
+    pub fn new(configuration: Rc<Configuration>) -> Self {
+        ServiceApiClient { configuration }
+    }
+}
+
+impl ServiceApi for ServiceApiClient {
+    fn get_module(
+        &self,
+        device_id: &str,
+        module_id: &str,
+    ) -> Box<Future<Item = ModuleSpec, Error = Error>> {
+        let uri = match self
+            .configuration
+            .uri(&format!("/devices/{}/modules/{}", device_id, module_id))
+        {
+            Ok(uri) => uri,
+            Err(err) => return Box::new(future::err(err)),
+        };
+        let user_agent = self.configuration.user_agent.clone();
+        let auth = self.configuration.auth.header_value();
+        let auth = match auth {
+            Ok(auth) => auth,
+            Err(err) => return Box::new(future::err(err)),
+        };
+
+        let build_request = move || -> Result<hyper::Request, Error> {
+            let mut req = hyper::Request::new(hyper::Method::Get, uri.clone());
+            if let Some(ref user_agent) = user_agent {
+                req.headers_mut().set(UserAgent::new(user_agent.clone()));
+            }
+            if let Some(ref auth) = auth {
+                req.headers_mut().set_raw("Authorization", auth.clone());
+            }
+            Ok(req)
+        };
+
+        let configuration = Rc::clone(&self.configuration);
+        // The target a verified `targets` metadata would list this
+        // response under, mirroring how the service scopes the module.
+        let target_name = format!("{}/modules/{}", device_id, module_id);
+
+        // GET is idempotent, so it always retries transient failures.
+        Box::new(
+            retry::execute(
+                Rc::clone(&self.configuration),
+                self.configuration.retry_policy.clone(),
+                true,
+                "get_module",
+                build_request,
+            )
+            .map_err(Error::from)
+            .and_then(|resp| resp.body().concat2().map_err(Error::from))
+            .and_then(move |body| {
+                if let (Some(store), Some(targets)) =
+                    (configuration.trust_store.as_ref(), configuration.trusted_targets.as_ref())
+                {
+                    let digest_bytes = digest::digest(&digest::SHA256, &body);
+                    let hash_hex = trust::hex_encode(digest_bytes.as_ref());
+                    store
+                        .verify_target(targets, &target_name, body.len() as u64, &hash_hex)
+                        .map_err(Error::from)?;
+                }
+                serde_json::from_slice::<ModuleSpec>(&body).map_err(Error::from)
+            }),
+        )
+    }
+
+    fn apply_configuration_content(
+        &self,
+        device_id: &str,
+        content: &DeploymentConfigInfo,
+    ) -> Box<Future<Item = (), Error = Error>> {
+        let uri = match self
+            .configuration
+            .uri(&format!("/devices/{}/applyConfigurationContent", device_id))
+        {
+            Ok(uri) => uri,
+            Err(err) => return Box::new(future::err(err)),
+        };
+        let body = match serde_json::to_vec(content) {
+            Ok(body) => Rc::new(body),
+            Err(err) => return Box::new(future::err(Error::from(err))),
+        };
+        let user_agent = self.configuration.user_agent.clone();
+        let auth = match self.configuration.auth.header_value() {
+            Ok(auth) => auth,
+            Err(err) => return Box::new(future::err(err)),
+        };
+
+        let build_request = move || -> Result<hyper::Request, Error> {
+            let mut req = hyper::Request::new(hyper::Method::Post, uri.clone());
+            req.headers_mut().set(ContentType::json());
+            if let Some(ref user_agent) = user_agent {
+                req.headers_mut().set(UserAgent::new(user_agent.clone()));
+            }
+            if let Some(ref auth) = auth {
+                req.headers_mut().set_raw("Authorization", auth.clone());
+            }
+            req.set_body((*body).clone());
+            Ok(req)
+        };
+
+        // POST is not idempotent: only retry if the caller opted in.
+        Box::new(
+            retry::execute(
+                Rc::clone(&self.configuration),
+                self.configuration.retry_policy.clone(),
+                self.configuration.retry_non_idempotent,
+                "apply_configuration_content",
+                build_request,
+            )
+            .map_err(Error::from)
+            .map(|_resp| ()),
+        )
+    }
+}
@@ -0,0 +1,698 @@
+//! TUF-style (The Update Framework) trust verification for deployment
+//! manifests. `TrustStore` walks root -> timestamp -> snapshot -> targets,
+//! checking signature thresholds, rollback protection and expiry at every
+//! step, so that a module image reference is only accepted once its hash
+//! and length have been confirmed against a signed `targets` entry.
+//!
+//! `ServiceApiClient::get_module` (see `super::service_api`) is the call
+//! site: when a `Configuration` is pinned with a trust root and a signed
+//! `targets` metadata, the raw response body is hashed and checked with
+//! `verify_target` before it is parsed into a `ModuleSpec`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64;
+use ring::signature;
+use serde::Serialize;
+use serde_json;
+
+#[derive(Debug, Fail)]
+pub enum TrustError {
+    #[fail(
+        display = "role {} met only {} of {} required signatures",
+        _0, _1, _2
+    )]
+    ThresholdNotMet(String, usize, u64),
+    #[fail(display = "role {} metadata has expired", _0)]
+    Expired(String),
+    #[fail(
+        display = "role {} version {} is not >= last seen version {}",
+        _0, _1, _2
+    )]
+    Rollback(String, u64, u64),
+    #[fail(display = "target {} content length does not match signed metadata", _0)]
+    LengthMismatch(String),
+    #[fail(display = "target {} content hash does not match signed metadata", _0)]
+    HashMismatch(String),
+    #[fail(display = "target {} is not listed in the signed targets metadata", _0)]
+    UnknownTarget(String),
+    #[fail(display = "role {} is not defined in the root metadata", _0)]
+    UnknownRole(String),
+    #[fail(display = "failed to canonicalize {} metadata for verification", _0)]
+    Canonicalize(String),
+    #[fail(
+        display = "role {} version {} does not match the version {} pinned by its parent metadata",
+        _0, _1, _2
+    )]
+    VersionMismatch(String, u64, u64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Key {
+    pub keytype: String,
+    pub keyval: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleKeys {
+    pub keyids: Vec<String>,
+    pub threshold: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub keyid: String,
+    pub sig: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signed<T> {
+    pub signed: T,
+    pub signatures: Vec<Signature>,
+}
+
+// These nested collections are part of the payload that gets re-serialized
+// and checked against a signature in `verify_role`, so they are
+// `BTreeMap`s rather than `HashMap`s: `HashMap`'s randomized iteration
+// order would make `serde_json::to_vec` produce different bytes than the
+// ones an external signer canonicalized and signed, for the exact same
+// logical metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootRole {
+    pub version: u64,
+    pub expires: i64,
+    pub keys: BTreeMap<String, Key>,
+    pub roles: BTreeMap<String, RoleKeys>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetInfo {
+    pub length: u64,
+    pub hashes: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetsRole {
+    pub version: u64,
+    pub expires: i64,
+    pub targets: BTreeMap<String, TargetInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaVersion {
+    pub version: u64,
+}
+
+/// The filename `snapshot.json` is keyed under in `timestamp`'s `meta`.
+const SNAPSHOT_META_NAME: &str = "snapshot.json";
+/// The filename `targets.json` is keyed under in `snapshot`'s `meta`.
+const TARGETS_META_NAME: &str = "targets.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRole {
+    pub version: u64,
+    pub expires: i64,
+    pub meta: BTreeMap<String, MetaVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimestampRole {
+    pub version: u64,
+    pub expires: i64,
+    pub meta: BTreeMap<String, MetaVersion>,
+}
+
+pub type Root = Signed<RootRole>;
+pub type Targets = Signed<TargetsRole>;
+pub type Snapshot = Signed<SnapshotRole>;
+pub type Timestamp = Signed<TimestampRole>;
+
+/// Verifies TUF role metadata against a pinned `root` and enforces
+/// rollback protection across calls by remembering the last-seen version
+/// of every role it has verified.
+pub struct TrustStore {
+    root: Root,
+    last_versions: HashMap<String, u64>,
+}
+
+impl TrustStore {
+    /// Pins `root` as the trust anchor, after checking `root`'s own
+    /// signatures/threshold/expiry against itself: TUF root metadata lists
+    /// its own verification key(s) and threshold under the `"root"` entry
+    /// of its `roles` map, so an unsigned or expired root is rejected here
+    /// rather than silently trusted for every role verified afterwards.
+    pub fn new(root: Root) -> Result<TrustStore, TrustError> {
+        let mut store = TrustStore {
+            root,
+            last_versions: HashMap::new(),
+        };
+        let root_metadata = store.root.clone();
+        store.verify_role("root", &root_metadata)?;
+        Ok(store)
+    }
+
+    /// Verifies the full root -> timestamp -> snapshot -> targets chain.
+    ///
+    /// Beyond each role's own signatures/expiry/rollback, this also pins
+    /// `snapshot` to the version `timestamp` currently vouches for, and
+    /// `targets` to the version `snapshot` currently vouches for. Without
+    /// this cross-check, an attacker could serve an individually valid,
+    /// unexpired, non-rolled-back `snapshot`/`targets` pair that simply
+    /// isn't the one the latest `timestamp` points at (a freeze/mix-and-
+    /// match attack), and it would pass anyway.
+    pub fn verify_chain(
+        &mut self,
+        timestamp: &Timestamp,
+        snapshot: &Snapshot,
+        targets: &Targets,
+    ) -> Result<(), TrustError> {
+        self.verify_role("timestamp", timestamp)?;
+
+        let pinned_snapshot = timestamp
+            .signed
+            .meta
+            .get(SNAPSHOT_META_NAME)
+            .ok_or_else(|| TrustError::UnknownRole("snapshot".to_owned()))?;
+        if snapshot.signed.version != pinned_snapshot.version {
+            return Err(TrustError::VersionMismatch(
+                "snapshot".to_owned(),
+                snapshot.signed.version,
+                pinned_snapshot.version,
+            ));
+        }
+
+        self.verify_role("snapshot", snapshot)?;
+
+        let pinned_targets = snapshot
+            .signed
+            .meta
+            .get(TARGETS_META_NAME)
+            .ok_or_else(|| TrustError::UnknownRole("targets".to_owned()))?;
+        if targets.signed.version != pinned_targets.version {
+            return Err(TrustError::VersionMismatch(
+                "targets".to_owned(),
+                targets.signed.version,
+                pinned_targets.version,
+            ));
+        }
+
+        self.verify_role("targets", targets)?;
+
+        Ok(())
+    }
+
+    /// Accepts a module image reference only if its length and SHA-256
+    /// hash match the entry for `name` in the signed `targets` metadata.
+    pub fn verify_target(
+        &self,
+        targets: &Targets,
+        name: &str,
+        length: u64,
+        sha256_hex: &str,
+    ) -> Result<(), TrustError> {
+        let info = targets
+            .signed
+            .targets
+            .get(name)
+            .ok_or_else(|| TrustError::UnknownTarget(name.to_owned()))?;
+
+        if info.length != length {
+            return Err(TrustError::LengthMismatch(name.to_owned()));
+        }
+
+        match info.hashes.get("sha256") {
+            Some(expected) if expected.eq_ignore_ascii_case(sha256_hex) => Ok(()),
+            _ => Err(TrustError::HashMismatch(name.to_owned())),
+        }
+    }
+
+    fn verify_role<T: Serialize>(&mut self, role: &str, signed: &Signed<T>) -> Result<(), TrustError>
+    where
+        Signed<T>: RoleMetadata,
+    {
+        let role_keys = self
+            .root
+            .signed
+            .roles
+            .get(role)
+            .ok_or_else(|| TrustError::UnknownRole(role.to_owned()))?;
+
+        let canonical = serde_json::to_vec(&signed.signed)
+            .map_err(|_| TrustError::Canonicalize(role.to_owned()))?;
+
+        // Count distinct *keys* that produced a valid signature, not
+        // signature entries: a `signatures` array with two entries for the
+        // same `keyid` must not count twice towards `threshold`.
+        let valid_keyids: HashSet<&str> = signed
+            .signatures
+            .iter()
+            .filter(|sig| role_keys.keyids.contains(&sig.keyid))
+            .filter_map(|sig| self.root.signed.keys.get(&sig.keyid).map(|key| (key, sig)))
+            .filter(|(key, sig)| verify_signature(key, &canonical, &sig.sig))
+            .map(|(_, sig)| sig.keyid.as_str())
+            .collect();
+        let valid = valid_keyids.len();
+
+        if valid < role_keys.threshold as usize {
+            return Err(TrustError::ThresholdNotMet(
+                role.to_owned(),
+                valid,
+                role_keys.threshold,
+            ));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        if signed.expires() <= now {
+            return Err(TrustError::Expired(role.to_owned()));
+        }
+
+        let version = signed.version();
+        let last = *self.last_versions.get(role).unwrap_or(&0);
+        if version < last {
+            return Err(TrustError::Rollback(role.to_owned(), version, last));
+        }
+        self.last_versions.insert(role.to_owned(), version);
+
+        Ok(())
+    }
+}
+
+/// Accessors shared by every TUF role's `signed` payload.
+trait RoleMetadata {
+    fn version(&self) -> u64;
+    fn expires(&self) -> i64;
+}
+
+impl RoleMetadata for Signed<RootRole> {
+    fn version(&self) -> u64 {
+        self.signed.version
+    }
+    fn expires(&self) -> i64 {
+        self.signed.expires
+    }
+}
+
+impl RoleMetadata for Signed<TargetsRole> {
+    fn version(&self) -> u64 {
+        self.signed.version
+    }
+    fn expires(&self) -> i64 {
+        self.signed.expires
+    }
+}
+
+impl RoleMetadata for Signed<SnapshotRole> {
+    fn version(&self) -> u64 {
+        self.signed.version
+    }
+    fn expires(&self) -> i64 {
+        self.signed.expires
+    }
+}
+
+impl RoleMetadata for Signed<TimestampRole> {
+    fn version(&self) -> u64 {
+        self.signed.version
+    }
+    fn expires(&self) -> i64 {
+        self.signed.expires
+    }
+}
+
+/// Lower-case hex encoding of a digest, in the form `targets.json`'s
+/// `hashes.sha256` entries use.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out
+}
+
+fn verify_signature(key: &Key, msg: &[u8], sig_b64: &str) -> bool {
+    let key_bytes = match base64::decode(&key.keyval) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+    let sig_bytes = match base64::decode(sig_b64) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+
+    match key.keytype.as_str() {
+        "ed25519" => {
+            let public_key = signature::UnparsedPublicKey::new(&signature::ED25519, key_bytes);
+            public_key.verify(msg, &sig_bytes).is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ring::rand::SystemRandom;
+    use ring::signature::Ed25519KeyPair;
+
+    fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Generates an ed25519 keypair and the `Key` metadata entry for it.
+    fn generate_key() -> (Ed25519KeyPair, Key) {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).expect("generate key");
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("parse key");
+        let keyval = base64::encode(key_pair.public_key().as_ref());
+        (
+            key_pair,
+            Key {
+                keytype: "ed25519".to_owned(),
+                keyval,
+            },
+        )
+    }
+
+    fn sign_timestamp(key_pair: &Ed25519KeyPair, payload: &TimestampRole, keyid: &str) -> Signature {
+        let canonical = serde_json::to_vec(payload).expect("serialize payload");
+        Signature {
+            keyid: keyid.to_owned(),
+            sig: base64::encode(key_pair.sign(&canonical).as_ref()),
+        }
+    }
+
+    /// `root` here is deliberately left unsigned (a threshold-0 `"root"`
+    /// entry) since these tests are about the `timestamp`/`snapshot`
+    /// roles it pins, not about root self-verification; see the
+    /// `root_self_verification` tests below for that.
+    fn root_with_timestamp_key(keyid: &str, key: Key, threshold: u64) -> Root {
+        let mut keys = BTreeMap::new();
+        keys.insert(keyid.to_owned(), key);
+        let mut roles = BTreeMap::new();
+        roles.insert(
+            "root".to_owned(),
+            RoleKeys {
+                keyids: Vec::new(),
+                threshold: 0,
+            },
+        );
+        roles.insert(
+            "timestamp".to_owned(),
+            RoleKeys {
+                keyids: vec![keyid.to_owned()],
+                threshold,
+            },
+        );
+        Signed {
+            signed: RootRole {
+                version: 1,
+                expires: now() + 3_600,
+                keys,
+                roles,
+            },
+            signatures: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn threshold_not_met_without_any_signatures() {
+        let (_key_pair, key) = generate_key();
+        let root = root_with_timestamp_key("k1", key, 1);
+        let mut store = TrustStore::new(root).expect("root should self-verify");
+
+        let timestamp = Signed {
+            signed: TimestampRole {
+                version: 1,
+                expires: now() + 3_600,
+                meta: BTreeMap::new(),
+            },
+            signatures: Vec::new(),
+        };
+
+        match store.verify_role("timestamp", &timestamp) {
+            Err(TrustError::ThresholdNotMet(role, valid, threshold)) => {
+                assert_eq!(role, "timestamp");
+                assert_eq!(valid, 0);
+                assert_eq!(threshold, 1);
+            }
+            other => panic!("expected ThresholdNotMet, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn duplicate_signatures_from_one_key_do_not_satisfy_a_higher_threshold() {
+        let (key_pair, key) = generate_key();
+        let root = root_with_timestamp_key("k1", key, 2);
+        let mut store = TrustStore::new(root).expect("root should self-verify");
+
+        let payload = TimestampRole {
+            version: 1,
+            expires: now() + 3_600,
+            meta: BTreeMap::new(),
+        };
+        let sig = sign_timestamp(&key_pair, &payload, "k1");
+        let timestamp = Signed {
+            signed: payload,
+            signatures: vec![sig.clone(), sig],
+        };
+
+        match store.verify_role("timestamp", &timestamp) {
+            Err(TrustError::ThresholdNotMet(_, valid, threshold)) => {
+                assert_eq!(valid, 1);
+                assert_eq!(threshold, 2);
+            }
+            other => panic!("expected ThresholdNotMet, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn single_valid_signature_satisfies_threshold_one() {
+        let (key_pair, key) = generate_key();
+        let root = root_with_timestamp_key("k1", key, 1);
+        let mut store = TrustStore::new(root).expect("root should self-verify");
+
+        let payload = TimestampRole {
+            version: 1,
+            expires: now() + 3_600,
+            meta: BTreeMap::new(),
+        };
+        let sig = sign_timestamp(&key_pair, &payload, "k1");
+        let timestamp = Signed {
+            signed: payload,
+            signatures: vec![sig],
+        };
+
+        assert!(store.verify_role("timestamp", &timestamp).is_ok());
+    }
+
+    #[test]
+    fn expired_metadata_is_rejected() {
+        let (key_pair, key) = generate_key();
+        let root = root_with_timestamp_key("k1", key, 1);
+        let mut store = TrustStore::new(root).expect("root should self-verify");
+
+        let payload = TimestampRole {
+            version: 1,
+            expires: now() - 1,
+            meta: BTreeMap::new(),
+        };
+        let sig = sign_timestamp(&key_pair, &payload, "k1");
+        let timestamp = Signed {
+            signed: payload,
+            signatures: vec![sig],
+        };
+
+        match store.verify_role("timestamp", &timestamp) {
+            Err(TrustError::Expired(role)) => assert_eq!(role, "timestamp"),
+            other => panic!("expected Expired, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rolled_back_version_is_rejected() {
+        let (key_pair, key) = generate_key();
+        let root = root_with_timestamp_key("k1", key, 1);
+        let mut store = TrustStore::new(root).expect("root should self-verify");
+
+        let newer = TimestampRole {
+            version: 2,
+            expires: now() + 3_600,
+            meta: BTreeMap::new(),
+        };
+        let newer_sig = sign_timestamp(&key_pair, &newer, "k1");
+        store
+            .verify_role("timestamp", &Signed { signed: newer, signatures: vec![newer_sig] })
+            .expect("newer version should verify");
+
+        let older = TimestampRole {
+            version: 1,
+            expires: now() + 3_600,
+            meta: BTreeMap::new(),
+        };
+        let older_sig = sign_timestamp(&key_pair, &older, "k1");
+        match store.verify_role("timestamp", &Signed { signed: older, signatures: vec![older_sig] }) {
+            Err(TrustError::Rollback(role, version, last)) => {
+                assert_eq!(role, "timestamp");
+                assert_eq!(version, 1);
+                assert_eq!(last, 2);
+            }
+            other => panic!("expected Rollback, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn verify_chain_rejects_snapshot_not_pinned_by_timestamp() {
+        let (key_pair, key) = generate_key();
+        let mut keys = BTreeMap::new();
+        keys.insert("k1".to_owned(), key);
+        let mut roles = BTreeMap::new();
+        roles.insert(
+            "root".to_owned(),
+            RoleKeys {
+                keyids: Vec::new(),
+                threshold: 0,
+            },
+        );
+        roles.insert(
+            "timestamp".to_owned(),
+            RoleKeys {
+                keyids: vec!["k1".to_owned()],
+                threshold: 1,
+            },
+        );
+        roles.insert(
+            "snapshot".to_owned(),
+            RoleKeys {
+                keyids: vec!["k1".to_owned()],
+                threshold: 1,
+            },
+        );
+        let root = Signed {
+            signed: RootRole {
+                version: 1,
+                expires: now() + 3_600,
+                keys,
+                roles,
+            },
+            signatures: Vec::new(),
+        };
+        let mut store = TrustStore::new(root).expect("root should self-verify");
+
+        let mut meta = BTreeMap::new();
+        meta.insert(SNAPSHOT_META_NAME.to_owned(), MetaVersion { version: 5 });
+        let timestamp_payload = TimestampRole {
+            version: 1,
+            expires: now() + 3_600,
+            meta,
+        };
+        let timestamp_sig = sign_timestamp(&key_pair, &timestamp_payload, "k1");
+        let timestamp = Signed {
+            signed: timestamp_payload,
+            signatures: vec![timestamp_sig],
+        };
+
+        // `snapshot` carries version 1, but `timestamp` pins version 5.
+        let snapshot_payload = SnapshotRole {
+            version: 1,
+            expires: now() + 3_600,
+            meta: BTreeMap::new(),
+        };
+        let canonical = serde_json::to_vec(&snapshot_payload).expect("serialize payload");
+        let snapshot_sig = Signature {
+            keyid: "k1".to_owned(),
+            sig: base64::encode(key_pair.sign(&canonical).as_ref()),
+        };
+        let snapshot = Signed {
+            signed: snapshot_payload,
+            signatures: vec![snapshot_sig],
+        };
+
+        let targets = Signed {
+            signed: TargetsRole {
+                version: 1,
+                expires: now() + 3_600,
+                targets: BTreeMap::new(),
+            },
+            signatures: Vec::new(),
+        };
+
+        match store.verify_chain(&timestamp, &snapshot, &targets) {
+            Err(TrustError::VersionMismatch(role, actual, expected)) => {
+                assert_eq!(role, "snapshot");
+                assert_eq!(actual, 1);
+                assert_eq!(expected, 5);
+            }
+            other => panic!("expected VersionMismatch, got {:?}", other),
+        }
+    }
+
+    fn self_signed_root(keyid: &str, key_pair: &Ed25519KeyPair, key: Key, threshold: u64, expires: i64) -> Root {
+        let mut keys = BTreeMap::new();
+        keys.insert(keyid.to_owned(), key);
+        let mut roles = BTreeMap::new();
+        roles.insert(
+            "root".to_owned(),
+            RoleKeys {
+                keyids: vec![keyid.to_owned()],
+                threshold,
+            },
+        );
+        let payload = RootRole {
+            version: 1,
+            expires,
+            keys,
+            roles,
+        };
+        let canonical = serde_json::to_vec(&payload).expect("serialize payload");
+        let sig = Signature {
+            keyid: keyid.to_owned(),
+            sig: base64::encode(key_pair.sign(&canonical).as_ref()),
+        };
+        Signed {
+            signed: payload,
+            signatures: vec![sig],
+        }
+    }
+
+    #[test]
+    fn new_accepts_a_root_that_validly_signs_itself() {
+        let (key_pair, key) = generate_key();
+        let root = self_signed_root("k1", &key_pair, key, 1, now() + 3_600);
+        assert!(TrustStore::new(root).is_ok());
+    }
+
+    #[test]
+    fn new_rejects_a_root_without_enough_self_signatures() {
+        let (key_pair, key) = generate_key();
+        // Signed by its one key, but asks for two.
+        let root = self_signed_root("k1", &key_pair, key, 2, now() + 3_600);
+
+        match TrustStore::new(root) {
+            Err(TrustError::ThresholdNotMet(role, valid, threshold)) => {
+                assert_eq!(role, "root");
+                assert_eq!(valid, 1);
+                assert_eq!(threshold, 2);
+            }
+            other => panic!("expected ThresholdNotMet, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn new_rejects_an_expired_root() {
+        let (key_pair, key) = generate_key();
+        let root = self_signed_root("k1", &key_pair, key, 1, now() - 1);
+
+        match TrustStore::new(root) {
+            Err(TrustError::Expired(role)) => assert_eq!(role, "root"),
+            other => panic!("expected Expired, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -5,11 +5,24 @@ extern crate serde_derive;
 
 #[macro_use]
 extern crate failure;
+extern crate base64;
 extern crate futures;
+extern crate hmac;
 extern crate hyper;
+extern crate hyper_rustls;
+extern crate hyperlocal;
+#[macro_use]
+extern crate log;
+extern crate rand;
+extern crate ring;
+extern crate rustls;
 extern crate serde;
 extern crate serde_json;
+extern crate sha2;
+extern crate tokio_core;
+extern crate tokio_timer;
 extern crate url;
+extern crate webpki_roots;
 
 pub mod apis;
 pub mod models;
\ No newline at end of file
@@ -0,0 +1,4 @@
+mod module;
+
+pub use self::module::DeploymentConfigInfo;
+pub use self::module::ModuleSpec;
@@ -0,0 +1,56 @@
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModuleSpec {
+    #[serde(rename = "name")]
+    name: String,
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(rename = "imageReference", skip_serializing_if = "Option::is_none")]
+    image_reference: Option<String>,
+}
+
+impl ModuleSpec {
+    pub fn new(name: String, type_: String) -> Self {
+        ModuleSpec {
+            name,
+            type_,
+            image_reference: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_image_reference(&mut self, image_reference: String) {
+        self.image_reference = Some(image_reference);
+    }
+
+    pub fn image_reference(&self) -> Option<&str> {
+        self.image_reference.as_ref().map(AsRef::as_ref)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeploymentConfigInfo {
+    #[serde(rename = "schemaVersion")]
+    schema_version: String,
+    #[serde(rename = "modules")]
+    modules: Vec<ModuleSpec>,
+}
+
+impl DeploymentConfigInfo {
+    pub fn new(schema_version: String, modules: Vec<ModuleSpec>) -> Self {
+        DeploymentConfigInfo {
+            schema_version,
+            modules,
+        }
+    }
+
+    pub fn schema_version(&self) -> &str {
+        &self.schema_version
+    }
+
+    pub fn modules(&self) -> &[ModuleSpec] {
+        &self.modules
+    }
+}